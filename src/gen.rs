@@ -1,8 +1,14 @@
 use proc_macro2::{Ident, TokenStream};
-use quote::{quote, ToTokens};
-use syn::{Data, DeriveInput, Field, FieldsNamed, FieldsUnnamed, Type, TypePath};
-
-use crate::parser::{attrs_to_customizes, fixed_value, has_customize, Customize};
+use quote::quote;
+use syn::{
+    parse_quote, Data, DeriveInput, Field, FieldsNamed, FieldsUnnamed, GenericArgument,
+    GenericParam, PathArguments, Type, TypePath,
+};
+
+use crate::parser::{
+    attrs_to_customizes, fixed_value, has_customize, len, only_if, range, recursion_limit,
+    scale_bias, with_path, Customize,
+};
 use quote::format_ident;
 use std::collections::HashMap;
 
@@ -12,14 +18,38 @@ pub type TraitMethods = HashMap<String, TokenStream>;
 
 pub(crate) fn transform(input: DeriveInput) -> TokenStream {
     let name = &input.ident;
+    let recursion_limit = recursion_limit(&attrs_to_customizes(&input.attrs));
+    let recursive_count_ident = format_ident!("RECURSIVE_COUNT_{}", name);
 
     let mut trait_methods = TraitMethods::new();
 
+    let terminating_ts = match &input.data {
+        Data::Struct(ds) => crate::gen_struct::generate_terminating(name, ds.clone()),
+        Data::Enum(de) => crate::gen_enum::generate_terminating(name, de.clone()),
+        Data::Union(_) => panic!("Unions are currently not supported"),
+    };
+
     let ts = match input.data {
         Data::Struct(ds) => crate::gen_struct::generate(name, &mut trait_methods, ds),
         Data::Enum(de) => crate::gen_enum::generate(name, &mut trait_methods, de),
         Data::Union(_) => panic!("Unions are currently not supported"),
     };
+    let ts = match only_if(&attrs_to_customizes(&input.attrs)) {
+        Some(predicate) => wrap_only_if(ts, &predicate, &format!("`{}`", name)),
+        None => ts,
+    };
+
+    let mut generics = input.generics;
+    for param in generics.params.clone().iter() {
+        if let GenericParam::Type(type_param) = param {
+            let ident = &type_param.ident;
+
+            generics.make_where_clause().predicates.push(parse_quote! {
+                rand::distributions::Standard: rand::distributions::Distribution<#ident>
+            });
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let mut tokens = TokenStream::new();
 
@@ -38,21 +68,44 @@ pub(crate) fn transform(input: DeriveInput) -> TokenStream {
         // Set the attribute unreachable code here, since there is a field attribute 'panic' in which
         // the type can not be generated
         #[allow(unreachable_code)]
-        impl rand::distributions::Distribution<#name> for rand::distributions::Standard {
-            fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> #name {
+        impl #impl_generics rand::distributions::Distribution<#name #ty_generics> for rand::distributions::Standard #where_clause {
+            fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> #name #ty_generics {
                 use rand::Rng;
 
+                thread_local! {
+                    #[allow(non_upper_case_globals)]
+                    static #recursive_count_ident: std::cell::Cell<u32> = std::cell::Cell::new(0);
+                }
+
+                struct RecursionGuard;
+
+                impl Drop for RecursionGuard {
+                    fn drop(&mut self) {
+                        #recursive_count_ident.with(|count| count.set(count.get() - 1));
+                    }
+                }
+
+                let depth = #recursive_count_ident.with(|count| {
+                    count.set(count.get() + 1);
+                    count.get()
+                });
+                let _guard = RecursionGuard;
+
+                if depth > #recursion_limit {
+                    return #terminating_ts;
+                }
+
                 #ts
             }
         }
 
-        impl #name {
+        impl #impl_generics #name #ty_generics #where_clause {
             pub fn generate_random() -> Self {
-                rand::random()
+                rand::random::<Self>()
             }
 
-            pub fn generate_random_customize<T: FnOnce(&mut Self)>(customize: T) -> Self {
-                let mut entity = rand::random();
+            pub fn generate_random_customize<F: FnOnce(&mut Self)>(customize: F) -> Self {
+                let mut entity = rand::random::<Self>();
 
                 customize(&mut entity);
 
@@ -68,15 +121,14 @@ fn trait_name(name: &Ident) -> Ident {
     format_ident!("{}For{}", TRAIT_NAME, name)
 }
 
-fn extract_type(t: &Type) -> (String, String) {
+fn type_name(t: &Type) -> String {
     match t {
-        Type::Path(tp) => extract_type_path(tp),
-        Type::Reference(r) => extract_type(&r.elem),
+        Type::Path(tp) => tp.path.segments.last().unwrap().ident.to_string(),
+        Type::Reference(r) => type_name(&r.elem),
         _ => panic!("This type is not supported: {:#?}", t),
     }
 }
 
-// TODO: This should actually be called recursively for when e.g. a vec in a vec must be generated
 fn generated_values(
     type_ident: &Ident,
     field_ident: Option<Ident>,
@@ -92,66 +144,193 @@ fn generated_values(
         },
     };
 
-    let (full_type, to_string) = extract_type(&ty);
-    let ts_value = generate_value(&to_string, &customizes);
     let value = if has_customize(&customizes, Customize::Panic) {
         quote! {
             panic!("This property can not be generated")
         }
     } else if has_customize(&customizes, Customize::Custom) {
-        add_to_trait_methods(type_ident, &field_ident, &ty, &to_string, trait_methods)
-    } else if to_string == "Option" {
-        // TODO: nicer way to get the inner type?
-        let inner =
-            &full_type[full_type.find("Option<").unwrap() + 7..full_type.rfind('>').unwrap()];
-        let ts_value = generate_value(inner, &customizes);
-
-        if has_customize(&customizes, Customize::AlwaysNone) {
-            quote! {
-                None
-            }
-        } else if has_customize(&customizes, Customize::AlwaysSome) {
-            quote! {
-                Some(#ts_value)
-            }
-        } else {
-            quote! {
-                if rng.gen() {
-                    Some(#ts_value)
-                } else {
-                    None
+        let ty_str = type_name(&ty);
+        add_to_trait_methods(type_ident, &field_ident, &ty, &ty_str, trait_methods)
+    } else if let Some(path) = with_path(&customizes) {
+        quote! {
+            #path(rng)
+        }
+    } else {
+        value_for_type(type_ident, &ty, &customizes, trait_methods)
+    };
+
+    let value = if let Some(predicate) = only_if(&customizes) {
+        let label = match &field_ident {
+            Some(i) => format!("field `{}` of `{}`", i, type_ident),
+            None => format!("a field of `{}`", type_ident),
+        };
+
+        wrap_only_if(value, &predicate, &label)
+    } else {
+        value
+    };
+
+    quote! {
+        #prefix #value
+    }
+}
+
+const ONLY_IF_ATTEMPTS: u32 = 1000;
+
+// Resamples `value` until `predicate` holds, bailing out with a clear panic
+// after a bounded number of attempts instead of looping forever on an
+// unsatisfiable constraint.
+fn wrap_only_if(value: TokenStream, predicate: &TokenStream, label: &str) -> TokenStream {
+    let message = format!(
+        "only_if constraint for {} could not be satisfied after {} attempts",
+        label, ONLY_IF_ATTEMPTS
+    );
+
+    quote! {
+        {
+            let mut attempts: u32 = 0;
+
+            loop {
+                let candidate = { #value };
+
+                if (|x: &_| #predicate)(&candidate) {
+                    break candidate;
+                }
+
+                attempts += 1;
+
+                if attempts >= #ONLY_IF_ATTEMPTS {
+                    panic!(#message);
                 }
             }
         }
-    } else if to_string == "Vec" {
-        if has_customize(&customizes, Customize::Empty) {
-            quote! {
-                vec![]
-            }
-        } else {
-            // TODO: recursion?
+    }
+}
+
+// Generates a value for `ty`, recursing into container types (`Option`, `Vec`,
+// `HashMap`, arrays, ...) so that nested collections like `Vec<Vec<T>>` or
+// `Option<HashSet<T>>` are handled rather than only their outermost layer.
+fn value_for_type(
+    type_ident: &Ident,
+    ty: &Type,
+    customizes: &[Customize],
+    trait_methods: &mut TraitMethods,
+) -> TokenStream {
+    match ty {
+        Type::Reference(r) => value_for_type(type_ident, &r.elem, customizes, trait_methods),
+        Type::Array(array) => {
+            let elem = value_for_element(type_ident, &array.elem, trait_methods);
+            let len = &array.len;
+
             quote! {
-                vec![#ts_value]
+                [(); #len].map(|_| #elem)
             }
         }
-    } else {
-        ts_value
-    };
+        Type::Path(tp) => {
+            let ty_str = tp.path.segments.last().unwrap().ident.to_string();
 
-    quote! {
-        #prefix #value
+            match ty_str.as_str() {
+                "Box" => {
+                    let inner_ts =
+                        value_for_type(type_ident, generic_arg(tp, 0), customizes, trait_methods);
+
+                    quote! { Box::new(#inner_ts) }
+                }
+                "Option" => {
+                    let inner_ts =
+                        value_for_type(type_ident, generic_arg(tp, 0), customizes, trait_methods);
+
+                    if has_customize(customizes, Customize::AlwaysNone) {
+                        quote! { None }
+                    } else if has_customize(customizes, Customize::AlwaysSome) {
+                        quote! { Some(#inner_ts) }
+                    } else {
+                        quote! {
+                            if rng.gen() {
+                                Some(#inner_ts)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                }
+                "Vec" | "HashSet" | "BTreeSet" => {
+                    if has_customize(customizes, Customize::Empty) {
+                        quote! { Default::default() }
+                    } else {
+                        let elem = value_for_element(type_ident, generic_arg(tp, 0), trait_methods);
+                        let collection = collection_path(&ty_str);
+                        let (start, end) = len(customizes);
+
+                        quote! {
+                            (0..rng.gen_range(#start..#end))
+                                .map(|_| #elem)
+                                .collect::<#collection<_>>()
+                        }
+                    }
+                }
+                "HashMap" | "BTreeMap" => {
+                    if has_customize(customizes, Customize::Empty) {
+                        quote! { Default::default() }
+                    } else {
+                        let key = value_for_element(type_ident, generic_arg(tp, 0), trait_methods);
+                        let val = value_for_element(type_ident, generic_arg(tp, 1), trait_methods);
+                        let collection = collection_path(&ty_str);
+                        let (start, end) = len(customizes);
+
+                        quote! {
+                            (0..rng.gen_range(#start..#end))
+                                .map(|_| (#key, #val))
+                                .collect::<#collection<_, _>>()
+                        }
+                    }
+                }
+                _ => generate_value(&ty_str, customizes),
+            }
+        }
+        _ => panic!("This type is not supported: {:#?}", ty),
     }
 }
 
-fn extract_type_path(tp: &TypePath) -> (String, String) {
-    let full_type = tp
-        .to_token_stream()
-        .to_string()
-        .split_whitespace()
-        .collect::<String>();
-    let to_string = &tp.path.segments.last().unwrap().ident.to_string();
+// Generates a value for an element nested inside a collection, without the
+// enclosing field's own customizations (those apply to the collection itself).
+fn value_for_element(
+    type_ident: &Ident,
+    ty: &Type,
+    trait_methods: &mut TraitMethods,
+) -> TokenStream {
+    value_for_type(type_ident, ty, &[], trait_methods)
+}
+
+fn generic_arg(tp: &TypePath, index: usize) -> &Type {
+    let segment = tp.path.segments.last().expect("Empty type path");
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(t) => Some(t),
+                _ => None,
+            })
+            .nth(index)
+            .unwrap_or_else(|| panic!("Expected a generic type argument on {}", segment.ident)),
+        _ => panic!("Expected generic arguments on {}", segment.ident),
+    }
+}
 
-    (full_type, to_string.to_string())
+fn collection_path(ty_str: &str) -> TokenStream {
+    match ty_str {
+        "Vec" => quote! { ::std::vec::Vec },
+        "HashSet" => quote! { ::std::collections::HashSet },
+        "BTreeSet" => quote! { ::std::collections::BTreeSet },
+        "HashMap" => quote! { ::std::collections::HashMap },
+        "BTreeMap" => quote! { ::std::collections::BTreeMap },
+        _ => unreachable!(
+            "collection_path called with non-collection type: {}",
+            ty_str
+        ),
+    }
 }
 
 fn add_to_trait_methods(
@@ -202,6 +381,13 @@ fn generate_value(ty_str: &str, customizes: &[Customize]) -> TokenStream {
         };
     }
 
+    let range = range(customizes);
+    let (scale, bias) = scale_bias(customizes);
+
+    if range.is_some() && (scale.is_some() || bias.is_some()) {
+        panic!("range cannot be combined with scale/bias on the same field");
+    }
+
     if has_customize(customizes, Customize::Default) {
         quote! {
             Default::default()
@@ -218,6 +404,18 @@ fn generate_value(ty_str: &str, customizes: &[Customize]) -> TokenStream {
         quote! {
             uuid::Uuid::new_v4()
         }
+    } else if let Some((start, end)) = range {
+        quote! {
+            rng.gen_range(#start..#end)
+        }
+    } else if scale.is_some() || bias.is_some() {
+        let ty: TokenStream = ty_str.parse().expect("Invalid numeric type for scale/bias");
+        let scale = scale.unwrap_or(1.0);
+        let bias = bias.unwrap_or(0.0);
+
+        quote! {
+            rng.gen::<#ty>() * (#scale as #ty) + (#bias as #ty)
+        }
     } else {
         quote! {
             rng.gen()
@@ -225,6 +423,77 @@ fn generate_value(ty_str: &str, customizes: &[Customize]) -> TokenStream {
     }
 }
 
+// Values used once a type's recursion guard trips, to bottom out a recursive
+// grammar instead of recursing into it again. Mirrors `value_for_type`'s walk
+// over the full `Type` (rather than a flattened name) so container types that
+// aren't themselves a recursion risk — arrays, `Box`, nested collections — are
+// still handled instead of panicking.
+fn terminating_value_for_type(ty: &Type, customizes: &[Customize]) -> TokenStream {
+    match ty {
+        Type::Reference(r) => terminating_value_for_type(&r.elem, customizes),
+        Type::Array(array) => {
+            let elem = terminating_value_for_type(&array.elem, &[]);
+            let len = &array.len;
+
+            quote! {
+                [(); #len].map(|_| #elem)
+            }
+        }
+        Type::Path(tp) => {
+            let ty_str = tp.path.segments.last().unwrap().ident.to_string();
+
+            match ty_str.as_str() {
+                "Box" => {
+                    let inner_ts = terminating_value_for_type(generic_arg(tp, 0), customizes);
+
+                    quote! { Box::new(#inner_ts) }
+                }
+                "Option" => quote! { None },
+                "Vec" | "HashSet" | "BTreeSet" | "HashMap" | "BTreeMap" => {
+                    quote! { Default::default() }
+                }
+                // Anything else isn't inherently unbounded, so fall back to the type's own
+                // generator instead of `Default::default()` — most derived types don't (and
+                // shouldn't have to) implement `Default` just to be usable as a nested field.
+                _ => generate_value(&ty_str, customizes),
+            }
+        }
+        _ => panic!("This type is not supported: {:#?}", ty),
+    }
+}
+
+fn terminating_values(field_ident: Option<Ident>, field: Field) -> TokenStream {
+    let customizes = attrs_to_customizes(&field.attrs);
+    let prefix = match &field_ident {
+        None => quote! {},
+        Some(i) => quote! {
+            #i:
+        },
+    };
+
+    let value = terminating_value_for_type(&field.ty, &customizes);
+
+    quote! {
+        #prefix #value
+    }
+}
+
+pub fn terminating_values_for_unnamed_fields(unnamed: FieldsUnnamed) -> Vec<TokenStream> {
+    unnamed
+        .unnamed
+        .into_iter()
+        .map(|r| terminating_values(None, r))
+        .collect()
+}
+
+pub fn terminating_values_for_named_fields(named: FieldsNamed) -> Vec<TokenStream> {
+    named
+        .named
+        .into_iter()
+        .map(|r| terminating_values(r.ident.clone(), r))
+        .collect()
+}
+
 pub fn generated_values_for_unnamed_fields(
     type_ident: &Ident,
     unnamed: FieldsUnnamed,
@@ -0,0 +1,124 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{DataEnum, Fields, Variant};
+
+use crate::gen::{
+    generated_values_for_named_fields, generated_values_for_unnamed_fields,
+    terminating_values_for_named_fields, terminating_values_for_unnamed_fields, TraitMethods,
+};
+use crate::parser::{attrs_to_customizes, weight};
+
+/// Builds a terminating variant out of terminating field values, used once the
+/// type's recursion guard trips. Prefers the first variant that doesn't
+/// reference the enum itself (e.g. `Lit(i32)` over `Add(Box<Expr>, Box<Expr>)`),
+/// falling back to the first variant if every one of them is self-referential.
+/// Picking deterministically (rather than drawing from `rng`) avoids recursing
+/// into the weighted selection while the guard is already tripped.
+pub(crate) fn generate_terminating(name: &Ident, de: DataEnum) -> TokenStream {
+    let variants: Vec<Variant> = de.variants.into_iter().collect();
+    let index = variants
+        .iter()
+        .position(|v| !references_self(name, v))
+        .unwrap_or(0);
+    let variant = variants
+        .into_iter()
+        .nth(index)
+        .expect("Enums must have at least one variant");
+    let variant_ident = &variant.ident;
+
+    match variant.fields {
+        Fields::Named(named) => {
+            let values = terminating_values_for_named_fields(named);
+
+            quote! { #name::#variant_ident { #(#values),* } }
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = terminating_values_for_unnamed_fields(unnamed);
+
+            quote! { #name::#variant_ident ( #(#values),* ) }
+        }
+        Fields::Unit => quote! { #name::#variant_ident },
+    }
+}
+
+// Crude but effective: look for `name` as a standalone identifier among a field's
+// top-level type tokens, which catches direct recursion (`Expr`) as well as the
+// common `Box<Expr>`/`Vec<Expr>` indirections without a full type walk. Tokens
+// inside delimited groups (tuple or array types) are not searched.
+fn references_self(name: &Ident, variant: &Variant) -> bool {
+    let name = name.to_string();
+
+    variant.fields.iter().any(|field| {
+        field
+            .ty
+            .to_token_stream()
+            .into_iter()
+            .any(|token| token.to_string() == name)
+    })
+}
+
+pub(crate) fn generate(
+    name: &Ident,
+    trait_methods: &mut TraitMethods,
+    de: DataEnum,
+) -> TokenStream {
+    let weights: Vec<u32> = de
+        .variants
+        .iter()
+        .map(|v| weight(&attrs_to_customizes(&v.attrs)))
+        .collect();
+    let total: u32 = weights.iter().sum();
+
+    if total == 0 {
+        panic!("At least one enum variant must have a non-zero weight");
+    }
+
+    let bodies: Vec<TokenStream> = de
+        .variants
+        .into_iter()
+        .map(|variant| variant_body(name, variant, trait_methods))
+        .collect();
+
+    if bodies.len() == 1 {
+        let body = &bodies[0];
+        return quote! { #body };
+    }
+
+    let mut cumulative = 0u32;
+    let mut arms = weights.iter().zip(bodies.iter()).map(|(w, body)| {
+        cumulative += w;
+        quote! {
+            if roll < #cumulative {
+                #body
+            }
+        }
+    });
+
+    let first = arms.next().unwrap();
+    let chained = arms.fold(first, |acc, arm| quote! { #acc else #arm });
+
+    quote! {
+        let roll = rng.gen_range(0..#total);
+        #chained else {
+            unreachable!()
+        }
+    }
+}
+
+fn variant_body(name: &Ident, variant: Variant, trait_methods: &mut TraitMethods) -> TokenStream {
+    let variant_ident = &variant.ident;
+
+    match variant.fields {
+        Fields::Named(named) => {
+            let values = generated_values_for_named_fields(name, named, trait_methods);
+
+            quote! { #name::#variant_ident { #(#values),* } }
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = generated_values_for_unnamed_fields(name, unnamed, trait_methods);
+
+            quote! { #name::#variant_ident ( #(#values),* ) }
+        }
+        Fields::Unit => quote! { #name::#variant_ident },
+    }
+}
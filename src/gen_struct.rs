@@ -0,0 +1,63 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{DataStruct, Fields};
+
+use crate::gen::{
+    generated_values_for_named_fields, generated_values_for_unnamed_fields,
+    terminating_values_for_named_fields, terminating_values_for_unnamed_fields, TraitMethods,
+};
+
+/// Builds `#name` out of terminating field values (`None` for `Option`, an empty
+/// collection for sequences and maps, and the field type's own generator otherwise),
+/// used once the type's recursion guard trips instead of generating fields normally.
+pub(crate) fn generate_terminating(name: &Ident, ds: DataStruct) -> TokenStream {
+    match ds.fields {
+        Fields::Named(named) => {
+            let values = terminating_values_for_named_fields(named);
+
+            quote! {
+                #name {
+                    #(#values),*
+                }
+            }
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = terminating_values_for_unnamed_fields(unnamed);
+
+            quote! {
+                #name (
+                    #(#values),*
+                )
+            }
+        }
+        Fields::Unit => quote! { #name },
+    }
+}
+
+pub(crate) fn generate(
+    name: &Ident,
+    trait_methods: &mut TraitMethods,
+    ds: DataStruct,
+) -> TokenStream {
+    match ds.fields {
+        Fields::Named(named) => {
+            let values = generated_values_for_named_fields(name, named, trait_methods);
+
+            quote! {
+                #name {
+                    #(#values),*
+                }
+            }
+        }
+        Fields::Unnamed(unnamed) => {
+            let values = generated_values_for_unnamed_fields(name, unnamed, trait_methods);
+
+            quote! {
+                #name (
+                    #(#values),*
+                )
+            }
+        }
+        Fields::Unit => quote! { #name },
+    }
+}
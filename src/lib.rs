@@ -0,0 +1,16 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod gen;
+mod gen_enum;
+mod gen_struct;
+mod parser;
+
+#[proc_macro_derive(RandGen, attributes(rand_derive))]
+pub fn derive_rand_gen(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    gen::transform(input).into()
+}
@@ -0,0 +1,231 @@
+use proc_macro2::TokenStream;
+use syn::{Attribute, Lit, Meta, NestedMeta};
+
+const ATTR_NAME: &str = "rand_derive";
+
+#[derive(Debug, Clone)]
+pub enum Customize {
+    Panic,
+    Custom,
+    AlwaysSome,
+    AlwaysNone,
+    Empty,
+    Default,
+    Fixed(TokenStream),
+    Weight(u32),
+    RecursionLimit(u32),
+    Range(TokenStream, TokenStream),
+    Scale(f64),
+    Bias(f64),
+    Len(TokenStream, TokenStream),
+    With(TokenStream),
+    OnlyIf(TokenStream),
+}
+
+pub fn attrs_to_customizes(attrs: &[Attribute]) -> Vec<Customize> {
+    attrs
+        .iter()
+        .filter(|a| a.path.is_ident(ATTR_NAME))
+        .flat_map(parse_attr)
+        .collect()
+}
+
+fn parse_attr(attr: &Attribute) -> Vec<Customize> {
+    let meta = attr.parse_meta().expect("Invalid rand_derive attribute");
+    let list = match meta {
+        Meta::List(list) => list,
+        _ => panic!("rand_derive attribute must be a list, e.g. #[rand_derive(panic)]"),
+    };
+
+    list.nested.iter().map(nested_to_customize).collect()
+}
+
+fn nested_to_customize(nested: &NestedMeta) -> Customize {
+    match nested {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("panic") => Customize::Panic,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("custom") => Customize::Custom,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("always_some") => Customize::AlwaysSome,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("always_none") => Customize::AlwaysNone,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("empty") => Customize::Empty,
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => Customize::Default,
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("fixed") => {
+            Customize::Fixed(fixed_lit_to_tokens(&nv.lit))
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("weight") => match &nv.lit {
+            Lit::Int(i) => Customize::Weight(i.base10_parse().expect("Invalid weight value")),
+            _ => panic!("weight attribute expects an integer literal, e.g. weight = 3"),
+        },
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("recursion_limit") => {
+            match &nv.lit {
+                Lit::Int(i) => Customize::RecursionLimit(
+                    i.base10_parse().expect("Invalid recursion_limit value"),
+                ),
+                _ => panic!("recursion_limit attribute expects an integer literal, e.g. recursion_limit = 5"),
+            }
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("range") => match &nv.lit {
+            Lit::Str(s) => {
+                let (start, end) = parse_range_str(&s.value());
+                Customize::Range(start, end)
+            }
+            _ => panic!("range attribute expects a string literal, e.g. range = \"1..100\""),
+        },
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("scale") => {
+            Customize::Scale(lit_to_f64(&nv.lit, "scale"))
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("bias") => {
+            Customize::Bias(lit_to_f64(&nv.lit, "bias"))
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("len") => match &nv.lit {
+            Lit::Str(s) => {
+                let (start, end) = parse_range_str(&s.value());
+                Customize::Len(start, end)
+            }
+            _ => panic!("len attribute expects a string literal, e.g. len = \"0..20\""),
+        },
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("with") => match &nv.lit {
+            Lit::Str(s) => Customize::With(
+                s.value()
+                    .parse()
+                    .expect("Invalid function path for with attribute"),
+            ),
+            _ => {
+                panic!("with attribute expects a string literal, e.g. with = \"path::to::my_gen\"")
+            }
+        },
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("only_if") => match &nv.lit {
+            Lit::Str(s) => Customize::OnlyIf(
+                s.value()
+                    .parse()
+                    .expect("Invalid predicate expression for only_if attribute"),
+            ),
+            _ => panic!("only_if attribute expects a string literal, e.g. only_if = \"*x > 0\""),
+        },
+        other => panic!("Unsupported rand_derive attribute: {:?}", other),
+    }
+}
+
+fn parse_range_str(s: &str) -> (TokenStream, TokenStream) {
+    let parts: Vec<&str> = s.splitn(2, "..").collect();
+    if parts.len() != 2 {
+        panic!(
+            "range/len attribute must be of the form \"start..end\", got: {}",
+            s
+        );
+    }
+
+    let start = parts[0].trim().parse().expect("Invalid range start");
+    let end = parts[1].trim().parse().expect("Invalid range end");
+
+    (start, end)
+}
+
+fn lit_to_f64(lit: &Lit, attr_name: &str) -> f64 {
+    match lit {
+        Lit::Float(f) => f.base10_parse().expect("Invalid float literal"),
+        Lit::Int(i) => i.base10_parse::<i64>().expect("Invalid integer literal") as f64,
+        _ => panic!(
+            "{} attribute expects a numeric literal, e.g. {} = 10.0",
+            attr_name, attr_name
+        ),
+    }
+}
+
+fn fixed_lit_to_tokens(lit: &Lit) -> TokenStream {
+    match lit {
+        Lit::Str(s) => s.value().parse().expect("Invalid fixed value expression"),
+        other => quote::quote! { #other },
+    }
+}
+
+// `Customize` can't derive `PartialEq` since some variants carry a `TokenStream`
+// payload, which has none. `has_customize` is only ever called against unit
+// variants, so comparing discriminants (ignoring any payload) is sufficient.
+pub fn has_customize(customizes: &[Customize], target: Customize) -> bool {
+    customizes
+        .iter()
+        .any(|c| std::mem::discriminant(c) == std::mem::discriminant(&target))
+}
+
+pub fn fixed_value(customizes: &[Customize]) -> Option<TokenStream> {
+    customizes.iter().find_map(|c| match c {
+        Customize::Fixed(ts) => Some(ts.clone()),
+        _ => None,
+    })
+}
+
+const DEFAULT_RECURSION_LIMIT: u32 = 5;
+
+/// Returns the container's declared `recursion_limit`, defaulting to `5` when unset.
+pub fn recursion_limit(customizes: &[Customize]) -> u32 {
+    customizes
+        .iter()
+        .find_map(|c| match c {
+            Customize::RecursionLimit(limit) => Some(*limit),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_RECURSION_LIMIT)
+}
+
+/// Returns the parsed `(start, end)` tokens of a `range` attribute, if present.
+pub fn range(customizes: &[Customize]) -> Option<(TokenStream, TokenStream)> {
+    customizes.iter().find_map(|c| match c {
+        Customize::Range(start, end) => Some((start.clone(), end.clone())),
+        _ => None,
+    })
+}
+
+/// Returns the `scale` and `bias` attributes, if either is present.
+pub fn scale_bias(customizes: &[Customize]) -> (Option<f64>, Option<f64>) {
+    let scale = customizes.iter().find_map(|c| match c {
+        Customize::Scale(s) => Some(*s),
+        _ => None,
+    });
+    let bias = customizes.iter().find_map(|c| match c {
+        Customize::Bias(b) => Some(*b),
+        _ => None,
+    });
+
+    (scale, bias)
+}
+
+/// Returns the `(start, end)` tokens of a sequence/map `len` attribute, defaulting
+/// to `0..9` (i.e. up to 8 elements) when unset.
+pub fn len(customizes: &[Customize]) -> (TokenStream, TokenStream) {
+    customizes
+        .iter()
+        .find_map(|c| match c {
+            Customize::Len(start, end) => Some((start.clone(), end.clone())),
+            _ => None,
+        })
+        .unwrap_or_else(|| (quote::quote! { 0 }, quote::quote! { 9 }))
+}
+
+/// Returns the function path of a `with` attribute, if present.
+pub fn with_path(customizes: &[Customize]) -> Option<TokenStream> {
+    customizes.iter().find_map(|c| match c {
+        Customize::With(path) => Some(path.clone()),
+        _ => None,
+    })
+}
+
+/// Returns the predicate expression of an `only_if` attribute, if present. The
+/// expression is evaluated as the body of a `|x: &_| ...` closure over the
+/// freshly generated candidate value.
+pub fn only_if(customizes: &[Customize]) -> Option<TokenStream> {
+    customizes.iter().find_map(|c| match c {
+        Customize::OnlyIf(predicate) => Some(predicate.clone()),
+        _ => None,
+    })
+}
+
+/// Returns the variant's declared `weight`, defaulting to `1` when unset.
+pub fn weight(customizes: &[Customize]) -> u32 {
+    customizes
+        .iter()
+        .find_map(|c| match c {
+            Customize::Weight(w) => Some(*w),
+            _ => None,
+        })
+        .unwrap_or(1)
+}
@@ -0,0 +1,15 @@
+use rand_derive2::RandGen;
+
+#[derive(Debug, RandGen)]
+struct Wrapper<T> {
+    inner: T,
+}
+
+#[test]
+fn generic_struct_generates_random_and_customize() {
+    let w: Wrapper<u8> = Wrapper::generate_random();
+    let _ = w.inner;
+
+    let w2 = Wrapper::<u8>::generate_random_customize(|w| w.inner = 7);
+    assert_eq!(w2.inner, 7);
+}
@@ -0,0 +1,18 @@
+use rand_derive2::RandGen;
+
+#[derive(Debug, RandGen)]
+struct Numbers {
+    #[rand_derive(range = "1..10")]
+    small: i32,
+    #[rand_derive(scale = 0.5, bias = 10.0)]
+    scaled: f64,
+}
+
+#[test]
+fn range_and_scale_bias_stay_within_bounds() {
+    for _ in 0..200 {
+        let n = Numbers::generate_random();
+        assert!((1..10).contains(&n.small));
+        assert!(n.scaled >= 10.0 && n.scaled < 10.5);
+    }
+}
@@ -0,0 +1,32 @@
+use rand_derive2::RandGen;
+
+#[derive(Debug, RandGen)]
+#[rand_derive(only_if = "matches!(x, Status::Active(n) if *n > 0)")]
+enum Status {
+    Active(i32),
+    Inactive,
+    Pending,
+}
+
+#[derive(Debug, RandGen)]
+struct Account {
+    #[rand_derive(only_if = "*x > 0")]
+    balance: i32,
+}
+
+#[test]
+fn field_level_only_if_resamples_until_satisfied() {
+    for _ in 0..100 {
+        assert!(Account::generate_random().balance > 0);
+    }
+}
+
+#[test]
+fn enum_level_only_if_resamples_a_multi_variant_enum() {
+    for _ in 0..100 {
+        match Status::generate_random() {
+            Status::Active(n) => assert!(n > 0),
+            other => panic!("only_if should have excluded {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use rand_derive2::RandGen;
+
+// A plain nested struct with no `Default` impl anywhere in the chain. This is
+// the common case the recursion guard must not break: `Address` only needs
+// `Standard: Distribution<Address>`, not `Address: Default`.
+#[derive(Debug, RandGen)]
+struct Address {
+    city: String,
+}
+
+#[derive(Debug, RandGen)]
+struct Person {
+    name: String,
+    address: Address,
+}
+
+// A self-referential enum via `Box`, the canonical recursive grammar the
+// recursion guard exists for.
+#[derive(Debug, RandGen)]
+enum Expr {
+    Add(Box<Expr>, Box<Expr>),
+    Lit(i32),
+}
+
+#[test]
+fn nested_non_default_struct_samples_fine() {
+    for _ in 0..100 {
+        let _ = Person::generate_random();
+    }
+}
+
+#[test]
+fn recursive_enum_terminates_instead_of_overflowing_the_stack() {
+    for _ in 0..1_000 {
+        let _ = Expr::generate_random();
+    }
+}
@@ -0,0 +1,29 @@
+use rand_derive2::RandGen;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, RandGen)]
+enum Weighted {
+    #[rand_derive(weight = 99)]
+    Common,
+    #[rand_derive(weight = 1)]
+    Rare,
+}
+
+#[test]
+fn weighted_variant_selection_favors_the_higher_weight() {
+    let mut common = 0;
+    let mut rare = 0;
+
+    for _ in 0..2_000 {
+        match Weighted::generate_random() {
+            Weighted::Common => common += 1,
+            Weighted::Rare => rare += 1,
+        }
+    }
+
+    assert!(
+        common > rare * 10,
+        "expected Common to dominate a 99:1 weighting, got common={}, rare={}",
+        common,
+        rare
+    );
+}
@@ -0,0 +1,18 @@
+use rand_derive2::RandGen;
+
+fn always_42<R: rand::Rng + ?Sized>(_rng: &mut R) -> i32 {
+    42
+}
+
+#[derive(Debug, RandGen)]
+struct WithCustomField {
+    #[rand_derive(with = "always_42")]
+    value: i32,
+}
+
+#[test]
+fn with_attribute_calls_the_custom_generator() {
+    for _ in 0..10 {
+        assert_eq!(WithCustomField::generate_random().value, 42);
+    }
+}